@@ -1,7 +1,9 @@
 //! Enumerate installed applications.
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::{fs, io, slice};
 
 use image::error::ImageError;
@@ -11,20 +13,26 @@ use xdg::BaseDirectories;
 
 use crate::svg::{self, Svg};
 
-/// Icon lookup paths in reverse order.
-const ICON_PATHS: &[(&str, &str)] = &[
-    ("/usr/share/icons/hicolor/32x32/apps/", "png"),
-    ("/usr/share/icons/hicolor/64x64/apps/", "png"),
-    ("/usr/share/icons/hicolor/256x256/apps/", "png"),
-    ("/usr/share/icons/hicolor/scalable/apps/", "svg"),
-    ("/usr/share/icons/hicolor/128x128/apps/", "png"),
-    ("/usr/share/pixmaps/", "svg"),
-    ("/usr/share/pixmaps/", "png"),
-];
+/// Extensions searched for icon files, in order of preference.
+const ICON_EXTENSIONS: &[&str] = &["png", "svg", "xpm"];
+
+/// Default `Threshold` value when a theme directory doesn't specify one.
+const DEFAULT_THRESHOLD: u32 = 2;
 
 /// Desired size for PNG icons at a scale factor of 1.
 const ICON_SIZE: u32 = 64;
 
+/// Generic icon shown when no themed icon could be resolved for an entry.
+const FALLBACK_ICON: &[u8] = include_bytes!("../assets/fallback-icon.png");
+
+/// Name recorded on [`Icon`] when it holds [`FALLBACK_ICON`].
+const FALLBACK_ICON_NAME: &str = "tzompantli-fallback";
+
+/// Largest `width`/`height` accepted from an XPM header. Icons are small by
+/// nature; this just keeps a malformed or hostile file from driving an
+/// oversized allocation.
+const MAX_XPM_DIMENSION: usize = 4096;
+
 #[derive(Debug)]
 pub struct DesktopEntries {
     entries: Vec<DesktopEntry>,
@@ -44,36 +52,48 @@ impl DesktopEntries {
 
         let mut desktop_entries = DesktopEntries { scale_factor, loader, entries: Vec::new() };
 
+        // Locale and desktop environment used to filter/localize entries.
+        let locale = std::env::var("LANG").unwrap_or_default();
+        let current_desktops = current_desktops();
+
         // Find all desktop files in these directories, then look for their icons and
         // executables.
         let icon_size = desktop_entries.icon_size();
         for dir_entry in dirs.iter().flat_map(|d| fs::read_dir(d.join("applications")).ok()) {
-            for desktop_file in dir_entry
+            for file_entry in dir_entry
                 .filter_map(|entry| entry.ok())
                 .filter(|entry| entry.file_type().map_or(false, |ft| ft.is_file()))
                 .filter(|entry| entry.file_name().to_string_lossy().ends_with(".desktop"))
-                .flat_map(|entry| fs::read_to_string(entry.path()).ok())
             {
-                let mut icon = None;
-                let mut exec = None;
-                let mut name = None;
-
-                for line in desktop_file.lines() {
-                    if let Some(value) = line.strip_prefix("Name=") {
-                        name = Some(value.to_owned());
-                    } else if let Some(value) = line.strip_prefix("Icon=") {
-                        icon = desktop_entries.loader.load(value, icon_size).ok();
-                    } else if let Some(value) = line.strip_prefix("Exec=") {
-                        exec = value.split(' ').next().map(String::from);
-                    }
-
-                    if icon.is_some() && exec.is_some() && name.is_some() {
-                        break;
-                    }
-                }
-
-                if let Some(((name, icon), exec)) = name.zip(icon).zip(exec) {
-                    desktop_entries.entries.push(DesktopEntry { icon, name, exec });
+                let Some(desktop_file) = fs::read_to_string(file_entry.path()).ok() else {
+                    continue;
+                };
+
+                let sections = parse_ini(&desktop_file);
+                let Some(parsed) = parse_desktop_entry(&sections, &locale, &current_desktops) else {
+                    continue;
+                };
+
+                let basename =
+                    file_entry.path().file_stem().map(|stem| stem.to_string_lossy().into_owned());
+                let icon = desktop_entries.loader.load_best(
+                    parsed.icon.as_deref(),
+                    basename.as_deref(),
+                    icon_size,
+                );
+
+                desktop_entries.entries.push(DesktopEntry {
+                    icon: icon.clone(),
+                    name: parsed.name.clone(),
+                    exec: parsed.exec,
+                });
+
+                for action in parsed.actions {
+                    desktop_entries.entries.push(DesktopEntry {
+                        icon: icon.clone(),
+                        name: format!("{} — {}", parsed.name, action.name),
+                        exec: action.exec,
+                    });
                 }
             }
         }
@@ -92,9 +112,7 @@ impl DesktopEntries {
         // Update every icon.
         let icon_size = self.icon_size();
         for entry in &mut self.entries {
-            if let Ok(icon) = self.loader.load(&entry.icon.name, icon_size) {
-                entry.icon = icon;
-            }
+            entry.icon = self.loader.reload(&entry.icon, icon_size);
         }
     }
 
@@ -124,7 +142,192 @@ impl DesktopEntries {
 pub struct DesktopEntry {
     pub icon: Icon,
     pub name: String,
-    pub exec: String,
+    pub exec: Vec<String>,
+}
+
+/// Desktop entry fields extracted from a parsed `.desktop` file, before icon
+/// resolution.
+struct ParsedDesktopEntry {
+    name: String,
+    icon: Option<String>,
+    exec: Vec<String>,
+    actions: Vec<ParsedAction>,
+}
+
+/// A `[Desktop Action <id>]` group, shown as its own launchable entry sharing
+/// the parent's icon.
+struct ParsedAction {
+    name: String,
+    exec: Vec<String>,
+}
+
+/// Desktop environment names from `$XDG_CURRENT_DESKTOP`, in priority order.
+fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|value| value.split(':').filter(|s| !s.is_empty()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Parse the `[Desktop Entry]` group of a `.desktop` file, applying the
+/// visibility rules from the spec and picking the best localized name.
+fn parse_desktop_entry(
+    sections: &[(String, HashMap<String, String>)],
+    locale: &str,
+    current_desktops: &[String],
+) -> Option<ParsedDesktopEntry> {
+    let (_, entries) = sections.iter().find(|(section, _)| section == "Desktop Entry")?;
+
+    if entries.get("Type").map_or(false, |kind| kind != "Application") {
+        return None;
+    }
+    if entries.get("NoDisplay").map(String::as_str) == Some("true") {
+        return None;
+    }
+    if entries.get("Hidden").map(String::as_str) == Some("true") {
+        return None;
+    }
+
+    if let Some(only_show_in) = entries.get("OnlyShowIn") {
+        let shown =
+            only_show_in.split(';').any(|desktop| current_desktops.iter().any(|d| d == desktop));
+        if !shown {
+            return None;
+        }
+    }
+    if let Some(not_show_in) = entries.get("NotShowIn") {
+        let hidden =
+            not_show_in.split(';').any(|desktop| current_desktops.iter().any(|d| d == desktop));
+        if hidden {
+            return None;
+        }
+    }
+
+    let name = localized_value(entries, "Name", locale)?;
+    let icon = entries.get("Icon").cloned();
+    let exec = exec_argv(entries);
+
+    let actions = entries
+        .get("Actions")
+        .map(|value| value.split(';').filter(|id| !id.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|id| parse_action(sections, id, locale))
+        .collect();
+
+    Some(ParsedDesktopEntry { name, icon, exec, actions })
+}
+
+/// Parse a single `[Desktop Action <id>]` group.
+fn parse_action(
+    sections: &[(String, HashMap<String, String>)],
+    id: &str,
+    locale: &str,
+) -> Option<ParsedAction> {
+    let section_name = format!("Desktop Action {id}");
+    let (_, entries) = sections.iter().find(|(section, _)| *section == section_name)?;
+
+    let name = localized_value(entries, "Name", locale)?;
+    let exec = exec_argv(entries);
+
+    Some(ParsedAction { name, exec })
+}
+
+/// Parse an entry's `Exec=` into argv, wrapping it in the user's terminal when
+/// `Terminal=true` is set.
+fn exec_argv(entries: &HashMap<String, String>) -> Vec<String> {
+    let mut exec = entries.get("Exec").map(|value| parse_exec(value)).unwrap_or_default();
+    if entries.get("Terminal").map(String::as_str) == Some("true") {
+        exec = wrap_in_terminal(exec);
+    }
+    exec
+}
+
+/// Pick the best-matching localized value for `key`, following the
+/// `key[lang_COUNTRY@MODIFIER]` fallback order from the desktop entry spec.
+fn localized_value(entries: &HashMap<String, String>, key: &str, locale: &str) -> Option<String> {
+    let (lang_country, modifier) = match locale.split_once('@') {
+        Some((lang_country, modifier)) => (lang_country, Some(modifier)),
+        None => (locale, None),
+    };
+    // Strip the codeset (if any) now that the modifier has already been split off,
+    // since POSIX locales are `lang_COUNTRY.codeset@modifier`.
+    let lang_country = lang_country.split('.').next().unwrap_or(lang_country);
+    let (lang, country) = match lang_country.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (lang_country, None),
+    };
+
+    let candidates = [
+        country
+            .zip(modifier)
+            .map(|(country, modifier)| format!("{key}[{lang}_{country}@{modifier}]")),
+        country.map(|country| format!("{key}[{lang}_{country}]")),
+        modifier.map(|modifier| format!("{key}[{lang}@{modifier}]")),
+        Some(format!("{key}[{lang}]")),
+    ];
+
+    candidates
+        .into_iter()
+        .flatten()
+        .find_map(|localized_key| entries.get(&localized_key).cloned())
+        .or_else(|| entries.get(key).cloned())
+}
+
+/// Parse an `Exec=` value into argv, stripping field codes and unescaping
+/// quoted arguments.
+fn parse_exec(exec: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = exec.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '\\' if in_quotes => current.extend(chars.next()),
+            ' ' if !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            },
+            '%' => match chars.next() {
+                Some('%') => current.push('%'),
+                Some('f' | 'F' | 'u' | 'U' | 'i' | 'c' | 'k') | None => {},
+                Some(other) => {
+                    current.push('%');
+                    current.push(other);
+                },
+            },
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Wrap a command's argv so it runs inside the user's terminal emulator.
+fn wrap_in_terminal(argv: Vec<String>) -> Vec<String> {
+    let terminal = std::env::var("TERMINAL").unwrap_or_else(|_| "xterm".to_owned());
+    [terminal, "-e".to_owned()].into_iter().chain(argv).collect()
+}
+
+/// Normalize an icon name for a second lookup attempt: lowercase it and strip
+/// a trailing version suffix like the `-2.10` in `Gimp-2.10`.
+fn normalize_icon_name(name: &str) -> String {
+    let without_version = match name.rsplit_once('-') {
+        Some((base, suffix))
+            if !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit() || c == '.') =>
+        {
+            base
+        },
+        _ => name,
+    };
+
+    without_version.to_lowercase()
 }
 
 /// Rendered icon.
@@ -138,76 +341,542 @@ pub struct Icon {
 /// Simple loader for app icons.
 #[derive(Debug)]
 struct IconLoader {
-    icons: HashMap<String, PathBuf>,
+    theme: IconThemeChain,
+    cache_dir: Option<PathBuf>,
 }
 
 impl IconLoader {
     /// Initialize the icon loader.
     ///
-    /// This will check all paths for available icons and store them for cheap
-    /// lookup.
+    /// This resolves the user's active icon theme and its full `Inherits=`
+    /// chain, so icon lookups behave like any other freedesktop-compliant
+    /// application launcher.
     fn new() -> Self {
-        let mut icons = HashMap::new();
-
-        // Check all paths for icons.
-        //
-        // Since the `ICON_PATHS` is in reverse order of our priority, we can just
-        // insert every new icon into `icons` and it will correctly return the
-        // closest match.
-        for (path, extension) in ICON_PATHS {
-            let mut read_dir = fs::read_dir(path).ok();
-            let entries = read_dir.iter_mut().flatten().flatten();
-            let files = entries.filter(|e| e.file_type().map_or(false, |e| e.is_file()));
-
-            // Iterate over all files in the directory.
-            for file in files {
-                let file_name = file.file_name().to_string_lossy().to_string();
-
-                // Store icon paths with the correct extension.
-                let name = file_name.rsplit_once('.').filter(|(_, ext)| ext == extension);
-                if let Some((name, _)) = name {
-                    let _ = icons.insert(name.to_owned(), file.path());
-                }
-            }
-        }
+        let cache_dir = BaseDirectories::with_prefix("tzompantli")
+            .ok()
+            .and_then(|dirs| dirs.create_cache_directory("icons").ok());
 
-        Self { icons }
+        Self { theme: IconThemeChain::new(), cache_dir }
     }
 
     /// Load image file as RGBA buffer.
     fn load(&self, icon: &str, size: u32) -> Result<Icon, Error> {
         let name = icon.into();
 
-        let path = self.icons.get(icon).ok_or(Error::NotFound)?;
-        let path_str = path.to_string_lossy();
+        let path = self.theme.resolve(icon, size).ok_or(Error::NotFound)?;
 
-        match &path_str[path_str.len() - 4..] {
-            ".png" => {
-                let mut image = ImageReader::open(path)?.decode()?;
+        if let Some((data, width)) = self.read_cache(&path, size) {
+            return Ok(Icon { data, width, name });
+        }
 
-                // Resize buffer if needed.
-                if image.width() != size && image.height() != size {
-                    image = image.resize(size, size, FilterType::CatmullRom);
-                }
+        let (data, width) = Self::rasterize(&path, size)?;
+        self.write_cache(&path, size, &data, width);
 
-                // Premultiply alpha.
-                let width = image.width() as usize;
-                let mut data = image.into_bytes();
-                for chunk in data.chunks_mut(4) {
-                    chunk[0] = (chunk[0] as f32 * chunk[3] as f32 / 255.).round() as u8;
-                    chunk[1] = (chunk[1] as f32 * chunk[3] as f32 / 255.).round() as u8;
-                    chunk[2] = (chunk[2] as f32 * chunk[3] as f32 / 255.).round() as u8;
-                }
+        Ok(Icon { data, width, name })
+    }
+
+    /// Try a desktop entry's icon name, falling back to normalized variants of
+    /// it and of the desktop file's own basename, and finally to the bundled
+    /// generic icon so every entry shows something.
+    fn load_best(&self, icon: Option<&str>, basename: Option<&str>, size: u32) -> Icon {
+        let candidates = icon
+            .into_iter()
+            .flat_map(|icon| [icon.to_owned(), normalize_icon_name(icon)])
+            .chain(
+                basename.into_iter().flat_map(|name| [name.to_owned(), normalize_icon_name(name)]),
+            );
+
+        for candidate in candidates {
+            if let Ok(icon) = self.load(&candidate, size) {
+                return icon;
+            }
+        }
 
-                Ok(Icon { data, width, name })
+        self.fallback_icon(size)
+    }
+
+    /// Reload a previously loaded icon at a new size, keeping the bundled
+    /// fallback if that's what was shown originally.
+    fn reload(&self, icon: &Icon, size: u32) -> Icon {
+        if icon.name == FALLBACK_ICON_NAME {
+            return self.fallback_icon(size);
+        }
+
+        self.load(&icon.name, size).unwrap_or_else(|_| self.fallback_icon(size))
+    }
+
+    /// Rasterize the bundled generic icon shown when nothing else resolves.
+    fn fallback_icon(&self, size: u32) -> Icon {
+        let image = image::load_from_memory(FALLBACK_ICON).expect("bundled fallback icon is valid");
+        let (data, width) = Self::rasterize_image(image, size);
+        Icon { data, width, name: FALLBACK_ICON_NAME.to_owned() }
+    }
+
+    /// Decode and resize an icon file into a premultiplied RGBA buffer.
+    fn rasterize(path: &Path, size: u32) -> Result<(Vec<u8>, usize), Error> {
+        let path_str = path.to_string_lossy();
+
+        match &path_str[path_str.len() - 4..] {
+            ".png" => {
+                let image = ImageReader::open(path)?.decode()?;
+                Ok(Self::rasterize_image(image, size))
             },
             ".svg" => {
                 let svg = Svg::from_path(path, size)?;
-                Ok(Icon { data: svg.data, width: svg.width, name })
+                Ok((svg.data, svg.width))
+            },
+            ".xpm" => {
+                let (data, width, height) = decode_xpm(path)?;
+                let image = image::RgbaImage::from_raw(width as u32, height as u32, data)
+                    .ok_or(Error::Xpm)?;
+                Ok(Self::rasterize_image(image::DynamicImage::ImageRgba8(image), size))
             },
             _ => unreachable!(),
         }
     }
+
+    /// Resize a decoded image to `size` and premultiply its alpha.
+    fn rasterize_image(mut image: image::DynamicImage, size: u32) -> (Vec<u8>, usize) {
+        // Resize buffer if needed.
+        if image.width() != size && image.height() != size {
+            image = image.resize(size, size, FilterType::CatmullRom);
+        }
+
+        // Premultiply alpha.
+        let width = image.width() as usize;
+        let mut data = image.into_bytes();
+        for chunk in data.chunks_mut(4) {
+            chunk[0] = (chunk[0] as f32 * chunk[3] as f32 / 255.).round() as u8;
+            chunk[1] = (chunk[1] as f32 * chunk[3] as f32 / 255.).round() as u8;
+            chunk[2] = (chunk[2] as f32 * chunk[3] as f32 / 255.).round() as u8;
+        }
+
+        (data, width)
+    }
+
+    /// Look up a rasterized icon in the on-disk cache.
+    ///
+    /// The cache key is derived from the source path, its modification time
+    /// and the target size, so edited or upgraded icon files and DPI changes
+    /// both transparently invalidate it.
+    fn read_cache(&self, path: &Path, size: u32) -> Option<(Vec<u8>, usize)> {
+        let cache_dir = self.cache_dir.as_ref()?;
+        let key = cache_key(path, size)?;
+        let bytes = fs::read(cache_dir.join(format!("{key:016x}.rgba"))).ok()?;
+
+        if bytes.len() < 4 {
+            return None;
+        }
+        let (header, data) = bytes.split_at(4);
+        let width = u32::from_le_bytes(header.try_into().ok()?) as usize;
+
+        Some((data.to_vec(), width))
+    }
+
+    /// Write a rasterized icon back to the on-disk cache.
+    fn write_cache(&self, path: &Path, size: u32, data: &[u8], width: usize) {
+        let (Some(cache_dir), Some(key)) = (&self.cache_dir, cache_key(path, size)) else { return };
+
+        let mut bytes = Vec::with_capacity(4 + data.len());
+        bytes.extend_from_slice(&(width as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+
+        let _ = fs::write(cache_dir.join(format!("{key:016x}.rgba")), bytes);
+    }
+}
+
+/// Derive a cache key from an icon's source path, modification time and
+/// target size.
+fn cache_key(path: &Path, size: u32) -> Option<u64> {
+    let mtime = fs::metadata(path).ok()?.modified().ok()?;
+
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    mtime.hash(&mut hasher);
+    size.hash(&mut hasher);
+
+    Some(hasher.finish())
+}
+
+/// Resolved chain of icon themes, from the active theme down to `hicolor`.
+#[derive(Debug)]
+struct IconThemeChain {
+    /// Themes to search, most specific first, ending in `hicolor`.
+    themes: Vec<IconTheme>,
+    /// Unthemed directories searched after every theme has been exhausted.
+    pixmap_dirs: Vec<PathBuf>,
+}
+
+impl IconThemeChain {
+    /// Resolve the active theme and its whole inheritance chain.
+    fn new() -> Self {
+        let base_dirs = BaseDirectories::new().expect("Unable to get XDG base directories");
+
+        let mut search_dirs = vec![base_dirs.get_data_home().join("icons")];
+        search_dirs.extend(base_dirs.get_data_dirs().into_iter().map(|dir| dir.join("icons")));
+
+        let mut themes = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(active_theme_name(&base_dirs));
+
+        while let Some(name) = queue.pop_front() {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+
+            if let Some(theme) = IconTheme::load(&search_dirs, &name) {
+                queue.extend(theme.inherits.iter().cloned());
+                themes.push(theme);
+            }
+        }
+
+        // `hicolor` is the spec-mandated fallback, even if nothing inherits from it.
+        if !seen.contains("hicolor") {
+            if let Some(theme) = IconTheme::load(&search_dirs, "hicolor") {
+                themes.push(theme);
+            }
+        }
+
+        let pixmap_dirs = vec![PathBuf::from("/usr/share/pixmaps")];
+
+        Self { themes, pixmap_dirs }
+    }
+
+    /// Resolve an icon name to a file path for the requested pixel size.
+    fn resolve(&self, name: &str, size: u32) -> Option<PathBuf> {
+        for theme in &self.themes {
+            if let Some(path) = theme.find_icon(name, size) {
+                return Some(path);
+            }
+        }
+
+        self.pixmap_dirs.iter().find_map(|dir| find_icon_file(dir, name))
+    }
+}
+
+/// Name of the active icon theme, read from the GTK settings, falling back to
+/// `"hicolor"` when none is configured.
+fn active_theme_name(base_dirs: &BaseDirectories) -> String {
+    base_dirs
+        .find_config_file("gtk-3.0/settings.ini")
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| {
+            parse_ini(&content)
+                .into_iter()
+                .find(|(section, _)| section == "Settings")
+                .and_then(|(_, entries)| entries.get("gtk-icon-theme-name").cloned())
+        })
+        .unwrap_or_else(|| "hicolor".to_owned())
+}
+
+/// A single parsed `index.theme` file.
+#[derive(Debug)]
+struct IconTheme {
+    inherits: Vec<String>,
+    directories: Vec<IconThemeDirectory>,
+}
+
+impl IconTheme {
+    /// Find and parse a theme's `index.theme` in the first search directory
+    /// that has one.
+    fn load(search_dirs: &[PathBuf], name: &str) -> Option<Self> {
+        let (theme_dir, content) = search_dirs.iter().find_map(|dir| {
+            let theme_dir = dir.join(name);
+            let content = fs::read_to_string(theme_dir.join("index.theme")).ok()?;
+            Some((theme_dir, content))
+        })?;
+
+        let sections = parse_ini(&content);
+        let main = sections.iter().find(|(section, _)| section == "Icon Theme")?;
+
+        let inherits = main
+            .1
+            .get("Inherits")
+            .map(|value| value.split(',').map(str::trim).map(String::from).collect())
+            .unwrap_or_default();
+
+        let directory_names = main
+            .1
+            .get("Directories")
+            .map(|value| value.split(',').map(str::trim))
+            .into_iter()
+            .flatten();
+
+        let directories = directory_names
+            .filter_map(|dir_name| {
+                let (_, entries) = sections.iter().find(|(section, _)| section == dir_name)?;
+                Some(IconThemeDirectory::parse(theme_dir.join(dir_name), entries))
+            })
+            .collect();
+
+        Some(Self { inherits, directories })
+    }
+
+    /// Find the best-matching icon file for the requested size in this theme.
+    fn find_icon(&self, name: &str, size: u32) -> Option<PathBuf> {
+        let mut fallback: Option<(u32, PathBuf)> = None;
+
+        for dir in &self.directories {
+            let Some(path) = find_icon_file(&dir.path, name) else { continue };
+
+            if dir.matches(size) {
+                return Some(path);
+            }
+
+            let distance = dir.distance(size);
+            if fallback.as_ref().map_or(true, |(best, _)| distance < *best) {
+                fallback = Some((distance, path));
+            }
+        }
+
+        fallback.map(|(_, path)| path)
+    }
+}
+
+/// A single `[<directory>]` section of an `index.theme` file.
+#[derive(Debug)]
+struct IconThemeDirectory {
+    path: PathBuf,
+    size: u32,
+    min_size: u32,
+    max_size: u32,
+    scale: u32,
+    kind: IconThemeDirectoryType,
+}
+
+/// The `Type=` of an icon theme directory.
+#[derive(Debug, PartialEq, Eq)]
+enum IconThemeDirectoryType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+impl IconThemeDirectory {
+    fn parse(path: PathBuf, entries: &HashMap<String, String>) -> Self {
+        let size = entries.get("Size").and_then(|v| v.parse().ok()).unwrap_or(ICON_SIZE);
+        let scale = entries.get("Scale").and_then(|v| v.parse().ok()).unwrap_or(1);
+        let threshold =
+            entries.get("Threshold").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_THRESHOLD);
+
+        let kind = match entries.get("Type").map(String::as_str) {
+            Some("Fixed") => IconThemeDirectoryType::Fixed,
+            Some("Scalable") => IconThemeDirectoryType::Scalable,
+            _ => IconThemeDirectoryType::Threshold,
+        };
+
+        let (min_size, max_size) = match kind {
+            IconThemeDirectoryType::Fixed => (size, size),
+            IconThemeDirectoryType::Scalable => (
+                entries.get("MinSize").and_then(|v| v.parse().ok()).unwrap_or(size),
+                entries.get("MaxSize").and_then(|v| v.parse().ok()).unwrap_or(size),
+            ),
+            IconThemeDirectoryType::Threshold => (
+                entries
+                    .get("MinSize")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(size.saturating_sub(threshold)),
+                entries
+                    .get("MaxSize")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(size.saturating_add(threshold)),
+            ),
+        };
+
+        Self { path, size, min_size, max_size, scale, kind }
+    }
+
+    /// Whether this directory is an exact match for the requested pixel size.
+    ///
+    /// `Size`/`MinSize`/`MaxSize` are in the directory's own `Scale` units
+    /// (e.g. a `32x32@2` directory holds 64px assets), so they're scaled up
+    /// before comparing against `size`, which is already a pixel count.
+    fn matches(&self, size: u32) -> bool {
+        match self.kind {
+            IconThemeDirectoryType::Fixed => self.size * self.scale == size,
+            IconThemeDirectoryType::Scalable | IconThemeDirectoryType::Threshold => {
+                self.min_size * self.scale <= size && size <= self.max_size * self.scale
+            },
+        }
+    }
+
+    /// Distance of the requested size from this directory's range, used to
+    /// pick the closest directory when nothing matches exactly.
+    fn distance(&self, size: u32) -> u32 {
+        match self.kind {
+            IconThemeDirectoryType::Fixed => (self.size * self.scale).abs_diff(size),
+            IconThemeDirectoryType::Scalable | IconThemeDirectoryType::Threshold => {
+                let (min, max) = (self.min_size * self.scale, self.max_size * self.scale);
+                if size < min {
+                    min - size
+                } else if size > max {
+                    size - max
+                } else {
+                    0
+                }
+            },
+        }
+    }
+}
+
+/// Look for `<dir>/<name>.<ext>` for every known icon extension.
+fn find_icon_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    ICON_EXTENSIONS.iter().map(|ext| dir.join(format!("{name}.{ext}"))).find(|path| path.is_file())
+}
+
+/// Decode the subset of XPM2/XPM3 needed for simple app icons into an RGBA
+/// buffer: the C-string color table followed by one C-string per pixel row.
+fn decode_xpm(path: &Path) -> Result<(Vec<u8>, usize, usize), Error> {
+    let content = fs::read_to_string(path)?;
+
+    let mut strings = content.lines().filter_map(|line| {
+        let start = line.find('"')?;
+        let end = line.rfind('"')?;
+        (end > start).then(|| &line[start + 1..end])
+    });
+
+    let mut header = strings.next().ok_or(Error::Xpm)?.split_whitespace();
+    let width: usize = header.next().and_then(|v| v.parse().ok()).ok_or(Error::Xpm)?;
+    let height: usize = header.next().and_then(|v| v.parse().ok()).ok_or(Error::Xpm)?;
+    let num_colors: usize = header.next().and_then(|v| v.parse().ok()).ok_or(Error::Xpm)?;
+    let chars_per_pixel: usize = header.next().and_then(|v| v.parse().ok()).ok_or(Error::Xpm)?;
+
+    if width == 0
+        || height == 0
+        || width > MAX_XPM_DIMENSION
+        || height > MAX_XPM_DIMENSION
+        || chars_per_pixel == 0
+        || chars_per_pixel > MAX_XPM_DIMENSION
+    {
+        return Err(Error::Xpm);
+    }
+    let data_len = width.checked_mul(height).and_then(|n| n.checked_mul(4)).ok_or(Error::Xpm)?;
+
+    let mut colors = HashMap::with_capacity(num_colors);
+    for _ in 0..num_colors {
+        let line = strings.next().ok_or(Error::Xpm)?;
+        let key = line.get(..chars_per_pixel).ok_or(Error::Xpm)?;
+        let definition = line.get(chars_per_pixel..).ok_or(Error::Xpm)?;
+        colors.insert(key.to_owned(), parse_xpm_color(definition));
+    }
+
+    let mut data = vec![0u8; data_len];
+    for y in 0..height {
+        let line = strings.next().ok_or(Error::Xpm)?;
+        for x in 0..width {
+            let start = x * chars_per_pixel;
+            let key = line.get(start..start + chars_per_pixel).ok_or(Error::Xpm)?;
+            let color = colors.get(key).copied().unwrap_or([0, 0, 0, 0]);
+            let offset = (y * width + x) * 4;
+            data[offset..offset + 4].copy_from_slice(&color);
+        }
+    }
+
+    Ok((data, width, height))
+}
+
+/// Parse an XPM color table entry (everything after the pixel key),
+/// preferring the `c` (color) definition over `g`/`g4`/`m` fallbacks.
+fn parse_xpm_color(definition: &str) -> [u8; 4] {
+    let tokens: Vec<&str> = definition.split_whitespace().collect();
+    let mut fallback: Option<&str> = None;
+
+    let mut i = 0;
+    while i + 1 < tokens.len() {
+        let (key, value) = (tokens[i], tokens[i + 1]);
+        if key == "c" {
+            return xpm_color_value(value);
+        }
+        if fallback.is_none() && matches!(key, "g" | "g4" | "m") {
+            fallback = Some(value);
+        }
+        i += 2;
+    }
+
+    fallback.map(xpm_color_value).unwrap_or([0, 0, 0, 255])
+}
+
+/// Resolve an XPM color value (`None`, `#rgb`/`#rrggbb`, or an X11 color
+/// name) into premultiplication-ready straight-alpha RGBA.
+fn xpm_color_value(value: &str) -> [u8; 4] {
+    if value.eq_ignore_ascii_case("none") {
+        return [0, 0, 0, 0];
+    }
+
+    if let Some(hex) = value.strip_prefix('#') {
+        if let Some(rgb) = parse_hex_color(hex) {
+            return rgb;
+        }
+    }
+
+    named_xpm_color(value).unwrap_or([0, 0, 0, 255])
+}
+
+/// Parse a `#` color made of 3 equal-width hex channels (`#rgb`, `#rrggbb`,
+/// `#rrrgggbbb`, ...), keeping only the top 8 bits of each channel.
+fn parse_hex_color(hex: &str) -> Option<[u8; 4]> {
+    let channel_len = hex.len() / 3;
+    if channel_len == 0 || hex.len() % 3 != 0 {
+        return None;
+    }
+
+    let mut channels = [0u8; 3];
+    for (channel, byte) in hex.as_bytes().chunks(channel_len).zip(channels.iter_mut()) {
+        let text = std::str::from_utf8(&channel[..2.min(channel_len)]).ok()?;
+        let value = u32::from_str_radix(text, 16).ok()?;
+        let bits = 4 * 2.min(channel_len) as u32;
+        // Scale a narrower-than-8-bit channel (e.g. the single nibble in `#rgb`)
+        // up to the full 0-255 range instead of reusing the raw digits as-is.
+        *byte = (value * 0xFF / ((1 << bits) - 1)) as u8;
+    }
+
+    Some([channels[0], channels[1], channels[2], 255])
+}
+
+/// A small table of the X11 color names commonly used in hand-written XPMs.
+fn named_xpm_color(name: &str) -> Option<[u8; 4]> {
+    let [r, g, b] = match name.to_ascii_lowercase().as_str() {
+        "white" => [255, 255, 255],
+        "black" => [0, 0, 0],
+        "red" => [255, 0, 0],
+        "green" => [0, 255, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" => [0, 255, 255],
+        "magenta" => [255, 0, 255],
+        "gray" | "grey" => [190, 190, 190],
+        "darkgray" | "darkgrey" => [169, 169, 169],
+        "lightgray" | "lightgrey" => [211, 211, 211],
+        _ => return None,
+    };
+
+    Some([r, g, b, 255])
+}
+
+/// Minimal parser for the INI-like format used by `.theme`/GTK config files.
+fn parse_ini(content: &str) -> Vec<(String, HashMap<String, String>)> {
+    let mut sections = Vec::new();
+    let mut current: Option<(String, HashMap<String, String>)> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            sections.extend(current.take());
+            current = Some((name.to_owned(), HashMap::new()));
+        } else if let (Some((_, entries)), Some((key, value))) =
+            (&mut current, line.split_once('='))
+        {
+            entries.insert(key.trim().to_owned(), value.trim().to_owned());
+        }
+    }
+
+    sections.extend(current);
+    sections
 }
 
 /// Icon loading error.
@@ -216,6 +885,7 @@ pub enum Error {
     Image(ImageError),
     Svg(svg::Error),
     Io(io::Error),
+    Xpm,
     NotFound,
 }
 
@@ -236,3 +906,66 @@ impl From<svg::Error> for Error {
         Self::Svg(error)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_exec_strips_field_codes() {
+        assert_eq!(parse_exec("foo %f %F %u %U %i %c %k bar"), vec!["foo", "bar"]);
+    }
+
+    #[test]
+    fn parse_exec_unescapes_percent() {
+        assert_eq!(parse_exec("foo %% bar"), vec!["foo", "%", "bar"]);
+    }
+
+    #[test]
+    fn parse_exec_handles_quoted_arguments() {
+        assert_eq!(
+            parse_exec(r#"foo "bar baz" "qu\"ux""#),
+            vec!["foo", "bar baz", "qu\"ux"]
+        );
+    }
+
+    #[test]
+    fn localized_value_prefers_lang_country_modifier() {
+        let mut entries = HashMap::new();
+        entries.insert("Name".to_owned(), "fallback".to_owned());
+        entries.insert("Name[ca_ES@valencia]".to_owned(), "valencia".to_owned());
+        entries.insert("Name[ca_ES]".to_owned(), "catalan-spain".to_owned());
+        entries.insert("Name[ca]".to_owned(), "catalan".to_owned());
+
+        assert_eq!(
+            localized_value(&entries, "Name", "ca_ES.UTF-8@valencia"),
+            Some("valencia".to_owned())
+        );
+    }
+
+    #[test]
+    fn localized_value_falls_back_through_lang_country_then_lang() {
+        let mut entries = HashMap::new();
+        entries.insert("Name".to_owned(), "fallback".to_owned());
+        entries.insert("Name[ca_ES]".to_owned(), "catalan-spain".to_owned());
+
+        assert_eq!(
+            localized_value(&entries, "Name", "ca_ES.UTF-8"),
+            Some("catalan-spain".to_owned())
+        );
+
+        let mut entries = HashMap::new();
+        entries.insert("Name".to_owned(), "fallback".to_owned());
+        entries.insert("Name[ca]".to_owned(), "catalan".to_owned());
+
+        assert_eq!(localized_value(&entries, "Name", "ca_ES.UTF-8"), Some("catalan".to_owned()));
+    }
+
+    #[test]
+    fn localized_value_falls_back_to_unlocalized_key() {
+        let mut entries = HashMap::new();
+        entries.insert("Name".to_owned(), "fallback".to_owned());
+
+        assert_eq!(localized_value(&entries, "Name", "ca_ES.UTF-8"), Some("fallback".to_owned()));
+    }
+}